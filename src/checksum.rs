@@ -0,0 +1,83 @@
+use std::hash::Hasher;
+
+/// A pluggable integrity check run over a block's *uncompressed* bytes
+/// before compression, and re-run over the decompressed bytes on the way
+/// back out. Kept behind a trait so the algorithm can vary independently
+/// of the compression codec (e.g. some formats carry their own trailing
+/// checksum, others carry none).
+pub trait Check: Send + Sync {
+    fn compute(&self, data: &[u8]) -> u64;
+}
+
+pub struct NoCheck;
+
+impl Check for NoCheck {
+    fn compute(&self, _data: &[u8]) -> u64 {
+        0
+    }
+}
+
+pub struct Crc32Check;
+
+impl Check for Crc32Check {
+    fn compute(&self, data: &[u8]) -> u64 {
+        crc32fast::hash(data) as u64
+    }
+}
+
+pub struct XxHashCheck;
+
+impl Check for XxHashCheck {
+    fn compute(&self, data: &[u8]) -> u64 {
+        let mut hasher = twox_hash::XxHash64::with_seed(0);
+        hasher.write(data);
+        hasher.finish()
+    }
+}
+
+/// Which `Check` a container was written with. Persisted in the footer
+/// alongside the compression format so a decompressor doesn't need to be
+/// told out of band.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumPolicy {
+    None,
+    Crc32,
+    XxHash,
+}
+
+impl ChecksumPolicy {
+    pub fn id(&self) -> u8 {
+        match self {
+            ChecksumPolicy::None => 0,
+            ChecksumPolicy::Crc32 => 1,
+            ChecksumPolicy::XxHash => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(ChecksumPolicy::None),
+            1 => Some(ChecksumPolicy::Crc32),
+            2 => Some(ChecksumPolicy::XxHash),
+            _ => None,
+        }
+    }
+
+    /// Parses a `--checksum` flag value, case-insensitively.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "none" => Some(ChecksumPolicy::None),
+            "crc32" => Some(ChecksumPolicy::Crc32),
+            "xxhash" | "xx64" => Some(ChecksumPolicy::XxHash),
+            _ => None,
+        }
+    }
+
+    pub fn checker(&self) -> Box<dyn Check> {
+        match self {
+            ChecksumPolicy::None => Box::new(NoCheck),
+            ChecksumPolicy::Crc32 => Box::new(Crc32Check),
+            ChecksumPolicy::XxHash => Box::new(XxHashCheck),
+        }
+    }
+}
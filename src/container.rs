@@ -0,0 +1,315 @@
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// Every compressed block is written as a little-endian `u32` length prefix,
+/// an 8-byte checksum of the *uncompressed* bytes (0 if the container's
+/// `ChecksumPolicy` is `None`), and then that many bytes of codec-specific
+/// compressed data. Framing is required because the parallel encoder emits
+/// one independent compressed stream per block concatenated back to back;
+/// without a length prefix a decoder would have no way to tell where one
+/// stream ends and the next begins.
+pub const BLOCK_HEADER_LEN: usize = 4 + 8;
+
+/// A framed block as read back from a container: the checksum recorded at
+/// compression time alongside the still-compressed payload.
+pub struct BlockFrame {
+    pub checksum: u64,
+    pub data: Vec<u8>,
+}
+
+/// Writes one framed block: length prefix, checksum, then `data`.
+pub fn write_block<W: Write>(writer: &mut W, data: &[u8], checksum: u64) -> io::Result<()> {
+    let len = u32::try_from(data.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "block too large to frame"))?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(&checksum.to_le_bytes())?;
+    writer.write_all(data)?;
+    Ok(())
+}
+
+/// Reads one framed block, returning `None` once the reader is exhausted
+/// exactly on a block boundary (a clean end of stream).
+pub fn read_block<R: Read>(reader: &mut R) -> io::Result<Option<BlockFrame>> {
+    let mut len_buf = [0u8; 4];
+    match reader.read_exact(&mut len_buf) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let mut checksum_buf = [0u8; 8];
+    reader.read_exact(&mut checksum_buf)?;
+    let checksum = u64::from_le_bytes(checksum_buf);
+
+    let len = u32::from_le_bytes(len_buf) as usize;
+    let mut data = vec![0u8; len];
+    reader.read_exact(&mut data)?;
+    Ok(Some(BlockFrame { checksum, data }))
+}
+
+/// Magic tag identifying this crate's container format, checked when
+/// loading a footer so a truncated or foreign file is rejected instead of
+/// silently misparsed.
+pub const MAGIC: [u8; 4] = *b"RZC1";
+
+/// One entry in the skip index: where a block's uncompressed bytes sit in
+/// the original stream, and where its compressed payload sits in this file.
+/// Lets a reader binary-search for the block covering an arbitrary
+/// uncompressed offset and seek straight to it.
+#[derive(Debug, Clone, Copy)]
+pub struct Checkpoint {
+    pub uncompressed_start: u64,
+    pub uncompressed_len: u64,
+    pub compressed_offset: u64,
+    pub compressed_len: u64,
+    /// Checksum of the block's uncompressed bytes, duplicated from the
+    /// frame header so random access can verify a block without also
+    /// reading the frame it skips past.
+    pub checksum: u64,
+}
+
+const CHECKPOINT_LEN: usize = 8 * 5;
+
+impl Checkpoint {
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.uncompressed_start.to_le_bytes())?;
+        writer.write_all(&self.uncompressed_len.to_le_bytes())?;
+        writer.write_all(&self.compressed_offset.to_le_bytes())?;
+        writer.write_all(&self.compressed_len.to_le_bytes())?;
+        writer.write_all(&self.checksum.to_le_bytes())?;
+        Ok(())
+    }
+
+    fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut buf = [0u8; CHECKPOINT_LEN];
+        reader.read_exact(&mut buf)?;
+        Ok(Checkpoint {
+            uncompressed_start: u64::from_le_bytes(buf[0..8].try_into().unwrap()),
+            uncompressed_len: u64::from_le_bytes(buf[8..16].try_into().unwrap()),
+            compressed_offset: u64::from_le_bytes(buf[16..24].try_into().unwrap()),
+            compressed_len: u64::from_le_bytes(buf[24..32].try_into().unwrap()),
+            checksum: u64::from_le_bytes(buf[32..40].try_into().unwrap()),
+        })
+    }
+}
+
+/// Fixed-size trailer written after the checkpoint index so a reader can
+/// find everything else by reading only the last `FOOTER_LEN` bytes of the
+/// file.
+pub struct Footer {
+    pub format_id: u8,
+    pub checksum_policy_id: u8,
+    /// The block size the container was written with. `BlockIndex::load`
+    /// checks every checkpoint's `uncompressed_len` against this as a cheap
+    /// sanity check on a corrupted or foreign index.
+    pub block_size: u64,
+    pub index_offset: u64,
+}
+
+pub const FOOTER_LEN: usize = 8 + 8 + 1 + 1 + MAGIC.len();
+
+impl Footer {
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.index_offset.to_le_bytes())?;
+        writer.write_all(&self.block_size.to_le_bytes())?;
+        writer.write_all(&[self.format_id])?;
+        writer.write_all(&[self.checksum_policy_id])?;
+        writer.write_all(&MAGIC)?;
+        Ok(())
+    }
+
+    fn read(buf: &[u8; FOOTER_LEN]) -> io::Result<Self> {
+        if buf[FOOTER_LEN - MAGIC.len()..] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a recognized container (bad magic)",
+            ));
+        }
+        Ok(Footer {
+            index_offset: u64::from_le_bytes(buf[0..8].try_into().unwrap()),
+            block_size: u64::from_le_bytes(buf[8..16].try_into().unwrap()),
+            format_id: buf[16],
+            checksum_policy_id: buf[17],
+        })
+    }
+}
+
+/// Writes the checkpoint index followed by its footer. Called once, after
+/// every compressed block has already been written.
+pub fn write_index<W: Write>(
+    writer: &mut W,
+    checkpoints: &[Checkpoint],
+    footer: &Footer,
+) -> io::Result<()> {
+    for checkpoint in checkpoints {
+        checkpoint.write(writer)?;
+    }
+    footer.write(writer)
+}
+
+/// The parsed skip index for a container, loaded once and then queried
+/// in-memory for random access.
+pub struct BlockIndex {
+    pub footer: Footer,
+    pub checkpoints: Vec<Checkpoint>,
+}
+
+impl BlockIndex {
+    /// Reads the footer from the end of `file`, then the checkpoint index
+    /// it points to. Leaves the file's position unspecified; seek before
+    /// doing anything else with it.
+    pub fn load<F: Read + Seek>(file: &mut F) -> io::Result<Self> {
+        let file_len = file.seek(SeekFrom::End(0))?;
+        if file_len < FOOTER_LEN as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "file is too small to contain a container footer",
+            ));
+        }
+
+        file.seek(SeekFrom::End(-(FOOTER_LEN as i64)))?;
+        let mut footer_buf = [0u8; FOOTER_LEN];
+        file.read_exact(&mut footer_buf)?;
+        let footer = Footer::read(&footer_buf)?;
+
+        let index_region_end = file_len - FOOTER_LEN as u64;
+        if footer.index_offset > index_region_end {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "container index_offset points past the end of the file",
+            ));
+        }
+
+        let index_len = index_region_end - footer.index_offset;
+        let num_checkpoints = index_len as usize / CHECKPOINT_LEN;
+
+        file.seek(SeekFrom::Start(footer.index_offset))?;
+        let mut checkpoints = Vec::with_capacity(num_checkpoints);
+        for _ in 0..num_checkpoints {
+            let checkpoint = Checkpoint::read(file)?;
+            if checkpoint.uncompressed_len > footer.block_size {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "checkpoint uncompressed_len exceeds the container's block_size",
+                ));
+            }
+            checkpoints.push(checkpoint);
+        }
+
+        Ok(BlockIndex { footer, checkpoints })
+    }
+
+    /// Binary-searches for the checkpoint covering `uncompressed_offset`,
+    /// relying on checkpoints being contiguous and in ascending order.
+    pub fn find(&self, uncompressed_offset: u64) -> Option<&Checkpoint> {
+        let idx = self.checkpoints.partition_point(|checkpoint| {
+            checkpoint.uncompressed_start + checkpoint.uncompressed_len <= uncompressed_offset
+        });
+        self.checkpoints
+            .get(idx)
+            .filter(|checkpoint| checkpoint.uncompressed_start <= uncompressed_offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn block_round_trips_through_write_and_read() {
+        let mut buf = Vec::new();
+        write_block(&mut buf, b"hello world", 0xDEAD_BEEF).unwrap();
+
+        let frame = read_block(&mut Cursor::new(buf)).unwrap().unwrap();
+        assert_eq!(frame.checksum, 0xDEAD_BEEF);
+        assert_eq!(frame.data, b"hello world");
+    }
+
+    #[test]
+    fn read_block_returns_none_at_a_clean_boundary() {
+        let buf: Vec<u8> = Vec::new();
+        assert!(read_block(&mut Cursor::new(buf)).unwrap().is_none());
+    }
+
+    #[test]
+    fn block_index_find_binary_searches_by_uncompressed_offset() {
+        let checkpoints = vec![
+            Checkpoint {
+                uncompressed_start: 0,
+                uncompressed_len: 10,
+                compressed_offset: 0,
+                compressed_len: 5,
+                checksum: 1,
+            },
+            Checkpoint {
+                uncompressed_start: 10,
+                uncompressed_len: 10,
+                compressed_offset: 5,
+                compressed_len: 5,
+                checksum: 2,
+            },
+        ];
+        let index = BlockIndex {
+            footer: Footer {
+                format_id: 0,
+                checksum_policy_id: 0,
+                block_size: 10,
+                index_offset: 0,
+            },
+            checkpoints,
+        };
+
+        assert_eq!(index.find(0).unwrap().checksum, 1);
+        assert_eq!(index.find(9).unwrap().checksum, 1);
+        assert_eq!(index.find(10).unwrap().checksum, 2);
+        assert!(index.find(20).is_none());
+    }
+
+    #[test]
+    fn load_rejects_an_index_offset_past_the_footer() {
+        let mut buf = Vec::new();
+        let footer = Footer {
+            format_id: 0,
+            checksum_policy_id: 0,
+            block_size: 1,
+            // Nothing precedes the footer in `buf`, so any nonzero
+            // index_offset points past the end of the file.
+            index_offset: 1_000_000,
+        };
+        footer.write(&mut buf).unwrap();
+
+        let result = BlockIndex::load(&mut Cursor::new(buf));
+        match result {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+            Ok(_) => panic!("expected load to reject an out-of-bounds index_offset"),
+        }
+    }
+
+    #[test]
+    fn load_rejects_a_checkpoint_longer_than_the_footers_block_size() {
+        let mut buf = Vec::new();
+        let checkpoint = Checkpoint {
+            uncompressed_start: 0,
+            uncompressed_len: 100,
+            compressed_offset: 0,
+            compressed_len: 1,
+            checksum: 0,
+        };
+        let footer = Footer {
+            format_id: 0,
+            checksum_policy_id: 0,
+            // Smaller than the checkpoint's uncompressed_len above, as if
+            // the footer had been tampered with or paired with the wrong
+            // index.
+            block_size: 10,
+            index_offset: 0,
+        };
+        write_index(&mut buf, &[checkpoint], &footer).unwrap();
+
+        let result = BlockIndex::load(&mut Cursor::new(buf));
+        match result {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+            Ok(_) => panic!("expected load to reject a checkpoint wider than block_size"),
+        }
+    }
+}
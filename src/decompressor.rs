@@ -0,0 +1,220 @@
+use crate::checksum::ChecksumPolicy;
+use crate::container::{self, read_block, BlockIndex};
+use crate::format::CompressionFormat;
+use crate::CompressionError;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+/// Reverses what `Compressor` produces: reads framed blocks back to back,
+/// stopping at the checkpoint index rather than at end of file, and feeds
+/// each one through the format's decoder, in order, directly into the
+/// output file. Takes no format or checksum policy of its own — both are
+/// recovered from the container's footer, same as `RandomAccessReader`,
+/// since the whole point of the footer is that a reader doesn't need to be
+/// told out of band.
+pub struct Decompressor;
+
+impl Decompressor {
+    pub fn new() -> Self {
+        Decompressor
+    }
+
+    pub fn decompress(
+        &self,
+        input_file: &mut File,
+        output_file: &mut File,
+    ) -> Result<(), CompressionError> {
+        let index = BlockIndex::load(input_file)?;
+        let format = CompressionFormat::from_id(index.footer.format_id)
+            .ok_or(CompressionError::InvalidData)?;
+        let checksum_policy = ChecksumPolicy::from_id(index.footer.checksum_policy_id)
+            .ok_or(CompressionError::InvalidData)?;
+        let checker = checksum_policy.checker();
+        input_file.seek(SeekFrom::Start(0))?;
+
+        let mut consumed = 0u64;
+        while consumed < index.footer.index_offset {
+            let frame = read_block(input_file)?.ok_or(CompressionError::InvalidData)?;
+            consumed += (container::BLOCK_HEADER_LEN + frame.data.len()) as u64;
+
+            let mut decoder = format.decoder(Cursor::new(frame.data));
+            let mut decompressed = Vec::new();
+            decoder.read_to_end(&mut decompressed)?;
+
+            if checksum_policy != ChecksumPolicy::None
+                && checker.compute(&decompressed) != frame.checksum
+            {
+                return Err(CompressionError::InvalidData);
+            }
+
+            output_file.write_all(&decompressed)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Decompressor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decompresses only the bytes needed to serve a given range of the original
+/// file, using the container's checkpoint index to seek straight to the
+/// covering block(s) instead of decoding from the start.
+pub struct RandomAccessReader {
+    format: CompressionFormat,
+    checksum_policy: ChecksumPolicy,
+    index: BlockIndex,
+}
+
+impl RandomAccessReader {
+    pub fn open(file: &mut File) -> Result<Self, CompressionError> {
+        let index = BlockIndex::load(file)?;
+        let format = CompressionFormat::from_id(index.footer.format_id)
+            .ok_or(CompressionError::InvalidData)?;
+        let checksum_policy = ChecksumPolicy::from_id(index.footer.checksum_policy_id)
+            .ok_or(CompressionError::InvalidData)?;
+        Ok(RandomAccessReader {
+            format,
+            checksum_policy,
+            index,
+        })
+    }
+
+    /// Returns the decompressed bytes covering `[start, start + len)` of the
+    /// original uncompressed stream.
+    pub fn read_range(
+        &self,
+        file: &mut File,
+        start: u64,
+        len: u64,
+    ) -> Result<Vec<u8>, CompressionError> {
+        let end = start + len;
+        let mut out = Vec::with_capacity(len as usize);
+        let mut offset = start;
+        let checker = self.checksum_policy.checker();
+
+        while offset < end {
+            let checkpoint = self.index.find(offset).ok_or(CompressionError::InvalidData)?;
+
+            file.seek(SeekFrom::Start(checkpoint.compressed_offset))?;
+            let mut compressed = vec![0u8; checkpoint.compressed_len as usize];
+            file.read_exact(&mut compressed)?;
+
+            let mut decoder = self.format.decoder(Cursor::new(compressed));
+            let mut block = Vec::new();
+            decoder.read_to_end(&mut block)?;
+
+            if self.checksum_policy != ChecksumPolicy::None
+                && checker.compute(&block) != checkpoint.checksum
+            {
+                return Err(CompressionError::InvalidData);
+            }
+
+            let block_end = checkpoint.uncompressed_start + checkpoint.uncompressed_len;
+            let want_from = (offset - checkpoint.uncompressed_start) as usize;
+            let want_to = (end.min(block_end) - checkpoint.uncompressed_start) as usize;
+            out.extend_from_slice(&block[want_from..want_to]);
+
+            offset = block_end;
+        }
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::{write_index, Checkpoint, Footer};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Builds a minimal, valid one-block container: a single deflate-encoded
+    /// block with its checksum recorded both in the frame header and the
+    /// checkpoint, matching what `Writer::write_chunk` and `Writer::finish`
+    /// produce in `main.rs`.
+    fn build_container(data: &[u8], checksum_policy: ChecksumPolicy, corrupt: bool) -> Vec<u8> {
+        let format = CompressionFormat::Deflate;
+        let checksum = checksum_policy.checker().compute(data);
+        let stored_checksum = if corrupt { checksum.wrapping_add(1) } else { checksum };
+
+        let mut encoder = format.encoder(6);
+        encoder.write_all(data).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut buf = Vec::new();
+        container::write_block(&mut buf, &compressed, stored_checksum).unwrap();
+
+        let checkpoint = Checkpoint {
+            uncompressed_start: 0,
+            uncompressed_len: data.len() as u64,
+            compressed_offset: container::BLOCK_HEADER_LEN as u64,
+            compressed_len: compressed.len() as u64,
+            checksum: stored_checksum,
+        };
+        let index_offset = buf.len() as u64;
+        let footer = Footer {
+            format_id: format.id(),
+            checksum_policy_id: checksum_policy.id(),
+            block_size: data.len() as u64,
+            index_offset,
+        };
+        write_index(&mut buf, &[checkpoint], &footer).unwrap();
+        buf
+    }
+
+    fn temp_file(name: &str) -> File {
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+        let unique = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!(
+            "rust_compress_test_{}_{}_{}",
+            std::process::id(),
+            unique,
+            name
+        ));
+        File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .unwrap()
+    }
+
+    #[test]
+    fn decompress_round_trips_a_valid_container() {
+        let data: &[u8] = b"the quick brown fox jumps over the lazy dog";
+        let mut input_file = temp_file("valid_in");
+        input_file
+            .write_all(&build_container(data, ChecksumPolicy::Crc32, false))
+            .unwrap();
+        input_file.seek(SeekFrom::Start(0)).unwrap();
+
+        let mut output_file = temp_file("valid_out");
+        Decompressor::new()
+            .decompress(&mut input_file, &mut output_file)
+            .unwrap();
+
+        output_file.seek(SeekFrom::Start(0)).unwrap();
+        let mut result = Vec::new();
+        output_file.read_to_end(&mut result).unwrap();
+        assert_eq!(result, data);
+    }
+
+    #[test]
+    fn decompress_rejects_a_corrupted_checksum() {
+        let data: &[u8] = b"the quick brown fox jumps over the lazy dog";
+        let mut input_file = temp_file("corrupt_in");
+        input_file
+            .write_all(&build_container(data, ChecksumPolicy::Crc32, true))
+            .unwrap();
+        input_file.seek(SeekFrom::Start(0)).unwrap();
+
+        let mut output_file = temp_file("corrupt_out");
+        let err = Decompressor::new()
+            .decompress(&mut input_file, &mut output_file)
+            .unwrap_err();
+        assert!(matches!(err, CompressionError::InvalidData));
+    }
+}
@@ -0,0 +1,223 @@
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::Compression;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// One of the compression codecs this crate knows how to produce.
+///
+/// Selected either from the output file's extension (`.gz`, `.xz`, `.zst`,
+/// `.br`, `.lz4`) or explicitly via the `--format` CLI flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionFormat {
+    Deflate,
+    Gz,
+    Xz,
+    Zstd,
+    Brotli,
+    Lz4,
+}
+
+impl CompressionFormat {
+    /// Infers a format from a path's extension, returning `None` for
+    /// extensions we don't recognize (callers should fall back to `--format`
+    /// or a sensible default in that case).
+    pub fn detect_from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Some(CompressionFormat::Gz),
+            Some("xz") => Some(CompressionFormat::Xz),
+            Some("zst") => Some(CompressionFormat::Zstd),
+            Some("br") => Some(CompressionFormat::Brotli),
+            Some("lz4") => Some(CompressionFormat::Lz4),
+            Some("deflate") => Some(CompressionFormat::Deflate),
+            _ => None,
+        }
+    }
+
+    /// Stable numeric id persisted in the container footer, so a reader can
+    /// recover the format without being told it out of band.
+    pub fn id(&self) -> u8 {
+        match self {
+            CompressionFormat::Deflate => 0,
+            CompressionFormat::Gz => 1,
+            CompressionFormat::Xz => 2,
+            CompressionFormat::Zstd => 3,
+            CompressionFormat::Brotli => 4,
+            CompressionFormat::Lz4 => 5,
+        }
+    }
+
+    /// Inverse of [`CompressionFormat::id`].
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(CompressionFormat::Deflate),
+            1 => Some(CompressionFormat::Gz),
+            2 => Some(CompressionFormat::Xz),
+            3 => Some(CompressionFormat::Zstd),
+            4 => Some(CompressionFormat::Brotli),
+            5 => Some(CompressionFormat::Lz4),
+            _ => None,
+        }
+    }
+
+    /// Parses a `--format` flag value, case-insensitively.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "deflate" => Some(CompressionFormat::Deflate),
+            "gz" | "gzip" => Some(CompressionFormat::Gz),
+            "xz" => Some(CompressionFormat::Xz),
+            "zst" | "zstd" => Some(CompressionFormat::Zstd),
+            "br" | "brotli" => Some(CompressionFormat::Brotli),
+            "lz4" => Some(CompressionFormat::Lz4),
+            _ => None,
+        }
+    }
+
+    /// Maps a level on this crate's common 0–9 scale (mirroring deflate's
+    /// native range) onto this format's own native range, since zstd
+    /// (1–22), brotli (0–11), and lz4 (0–12) each use wider scales.
+    pub fn normalize_level(&self, level: u32) -> u32 {
+        let level = level.min(9);
+        match self {
+            CompressionFormat::Deflate | CompressionFormat::Gz | CompressionFormat::Xz => level,
+            CompressionFormat::Zstd => (level * 22 / 9).max(1),
+            CompressionFormat::Brotli => level * 11 / 9,
+            CompressionFormat::Lz4 => level * 12 / 9,
+        }
+    }
+
+    /// Builds a fresh encoder for this format at the given level. The
+    /// returned encoder buffers into an in-memory `Vec<u8>`; call
+    /// `BlockEncoder::finish` to retrieve the compressed bytes.
+    pub fn encoder(&self, level: u32) -> Box<dyn BlockEncoder> {
+        match self {
+            CompressionFormat::Deflate => {
+                Box::new(DeflateEncoder::new(Vec::new(), Compression::new(level)))
+            }
+            CompressionFormat::Gz => {
+                Box::new(GzEncoder::new(Vec::new(), Compression::new(level)))
+            }
+            CompressionFormat::Xz => Box::new(xz2::write::XzEncoder::new(Vec::new(), level)),
+            CompressionFormat::Zstd => Box::new(
+                zstd::stream::Encoder::new(Vec::new(), level as i32)
+                    .expect("failed to initialize zstd encoder"),
+            ),
+            CompressionFormat::Brotli => Box::new(BrotliEncoder::new(level)),
+            CompressionFormat::Lz4 => Box::new(Lz4BlockEncoder::new(level)),
+        }
+    }
+
+    /// Wraps `reader` in the matching decoder for this format, undoing
+    /// whatever `encoder` produced.
+    pub fn decoder<R: Read + 'static>(&self, reader: R) -> Box<dyn Read> {
+        match self {
+            CompressionFormat::Deflate => Box::new(flate2::read::DeflateDecoder::new(reader)),
+            CompressionFormat::Gz => Box::new(flate2::read::GzDecoder::new(reader)),
+            CompressionFormat::Xz => Box::new(xz2::read::XzDecoder::new(reader)),
+            CompressionFormat::Zstd => Box::new(
+                zstd::stream::Decoder::new(reader).expect("failed to initialize zstd decoder"),
+            ),
+            CompressionFormat::Brotli => Box::new(brotli::Decompressor::new(reader, 4096)),
+            CompressionFormat::Lz4 => {
+                Box::new(lz4::Decoder::new(reader).expect("failed to initialize lz4 decoder"))
+            }
+        }
+    }
+}
+
+/// A `Write`r that compresses into an owned buffer and can be drained once
+/// the caller is done feeding it data. Lets `CompressionWorker` treat every
+/// codec identically instead of hard-coding `DeflateEncoder`.
+pub trait BlockEncoder: Write {
+    fn finish(self: Box<Self>) -> io::Result<Vec<u8>>;
+}
+
+impl BlockEncoder for DeflateEncoder<Vec<u8>> {
+    fn finish(self: Box<Self>) -> io::Result<Vec<u8>> {
+        (*self).finish()
+    }
+}
+
+impl BlockEncoder for GzEncoder<Vec<u8>> {
+    fn finish(self: Box<Self>) -> io::Result<Vec<u8>> {
+        (*self).finish()
+    }
+}
+
+impl BlockEncoder for xz2::write::XzEncoder<Vec<u8>> {
+    fn finish(self: Box<Self>) -> io::Result<Vec<u8>> {
+        (*self).finish()
+    }
+}
+
+impl<'a> BlockEncoder for zstd::stream::Encoder<'a, Vec<u8>> {
+    fn finish(self: Box<Self>) -> io::Result<Vec<u8>> {
+        (*self).finish()
+    }
+}
+
+/// Wraps `brotli::CompressorWriter`, which doesn't expose a `finish` that
+/// hands back the inner buffer directly.
+struct BrotliEncoder {
+    inner: brotli::CompressorWriter<Vec<u8>>,
+}
+
+impl BrotliEncoder {
+    fn new(level: u32) -> Self {
+        BrotliEncoder {
+            inner: brotli::CompressorWriter::new(Vec::new(), 4096, level, 22),
+        }
+    }
+}
+
+impl Write for BrotliEncoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl BlockEncoder for BrotliEncoder {
+    fn finish(mut self: Box<Self>) -> io::Result<Vec<u8>> {
+        self.inner.flush()?;
+        Ok(std::mem::take(self.inner.get_mut()))
+    }
+}
+
+/// Wraps `lz4::Encoder`, whose `finish` returns `(W, io::Result<()>)` rather
+/// than `io::Result<Vec<u8>>`.
+struct Lz4BlockEncoder {
+    inner: Option<lz4::Encoder<Vec<u8>>>,
+}
+
+impl Lz4BlockEncoder {
+    fn new(level: u32) -> Self {
+        let encoder = lz4::EncoderBuilder::new()
+            .level(level)
+            .build(Vec::new())
+            .expect("failed to initialize lz4 encoder");
+        Lz4BlockEncoder {
+            inner: Some(encoder),
+        }
+    }
+}
+
+impl Write for Lz4BlockEncoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.as_mut().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.as_mut().unwrap().flush()
+    }
+}
+
+impl BlockEncoder for Lz4BlockEncoder {
+    fn finish(mut self: Box<Self>) -> io::Result<Vec<u8>> {
+        let (buf, result) = self.inner.take().unwrap().finish();
+        result?;
+        Ok(buf)
+    }
+}